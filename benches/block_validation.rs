@@ -0,0 +1,90 @@
+// Sweeps num_threads / num_txns / tx_size / p_inside and reports txns/sec (via Criterion's
+// Elements throughput, computed from num_txns) and speedup vs. the sequential baseline, for the
+// hashing and validation stages separately. This is what makes the "parallel beats sequential"
+// claim measurable, and what would catch per-thread overhead eroding gains at high thread
+// counts. MB/sec for a given workload is recoverable from its tx_size
+// (encoded in the benchmark id, e.g. "10000x200x0.1") times its reported txns/sec, so there's no
+// need to re-run the same workload under a second throughput unit. Run with `cargo bench`.
+
+extern crate criterion;
+extern crate parallel_ttor;
+extern crate rayon;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use parallel_ttor::{build_index, gen_block, setup_bounds, validate_all, validate_sequential, IndexedBlock};
+use rayon::ThreadPoolBuilder;
+use std::sync::Arc;
+
+const NUM_THREADS: &[usize] = &[1, 2, 4, 8];
+const NUM_TXNS: &[usize] = &[10_000, 100_000];
+const TX_SIZE: &[usize] = &[200, 1000];
+const P_INSIDE: &[f32] = &[0.0, 0.1, 0.5];
+
+fn bench_hashing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hashing");
+    for &num_txns in NUM_TXNS {
+        for &tx_size in TX_SIZE {
+            for &p_inside in P_INSIDE {
+                let block = gen_block(num_txns, tx_size, 0, p_inside);
+                let indexed_block = IndexedBlock::new(Arc::new(block));
+                let workload = format!("{}x{}x{}", num_txns, tx_size, p_inside);
+                group.throughput(Throughput::Elements(indexed_block.num_txns() as u64));
+
+                group.bench_function(BenchmarkId::new("sequential", &workload), |b| {
+                    let bounds = setup_bounds(1, &indexed_block);
+                    b.iter(|| black_box(build_index(&indexed_block, &bounds)))
+                });
+
+                for &num_threads in NUM_THREADS {
+                    let pool = ThreadPoolBuilder::new()
+                        .num_threads(num_threads)
+                        .build()
+                        .unwrap();
+                    let bounds = setup_bounds(num_threads * 4, &indexed_block);
+                    let id = BenchmarkId::new(format!("parallel/{}", num_threads), &workload);
+                    group.bench_function(id, |b| {
+                        b.iter(|| pool.install(|| black_box(build_index(&indexed_block, &bounds))))
+                    });
+                }
+            }
+        }
+    }
+    group.finish();
+}
+
+fn bench_validation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validation");
+    for &num_txns in NUM_TXNS {
+        for &tx_size in TX_SIZE {
+            for &p_inside in P_INSIDE {
+                let block = gen_block(num_txns, tx_size, 0, p_inside);
+                let indexed_block = IndexedBlock::new(Arc::new(block));
+                let workload = format!("{}x{}x{}", num_txns, tx_size, p_inside);
+                group.throughput(Throughput::Elements(indexed_block.num_txns() as u64));
+
+                group.bench_function(BenchmarkId::new("sequential", &workload), |b| {
+                    b.iter(|| black_box(validate_sequential(&indexed_block)))
+                });
+
+                for &num_threads in NUM_THREADS {
+                    let pool = ThreadPoolBuilder::new()
+                        .num_threads(num_threads)
+                        .build()
+                        .unwrap();
+                    let bounds = setup_bounds(num_threads * 4, &indexed_block);
+                    let index = build_index(&indexed_block, &bounds);
+                    let id = BenchmarkId::new(format!("parallel/{}", num_threads), &workload);
+                    group.bench_function(id, |b| {
+                        b.iter(|| {
+                            pool.install(|| black_box(validate_all(&indexed_block, &bounds, &index)))
+                        })
+                    });
+                }
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_hashing, bench_validation);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+// Parses a raw serialized block into an index of transaction byte offsets and lengths.
+//
+// Real transactions vary in length, so we can't carve the block into equal `tx_size` chunks.
+// Instead we stream through the raw bytes exactly once, recording where each transaction's
+// payload starts and how long it is, and build the thread bounds from that. The resulting
+// index is immutable and shared across every hashing and validation thread via `Arc`, so it
+// only needs to be built once per block no matter how many threads read it.
+
+use std::sync::Arc;
+
+// (byte_offset, byte_len) of a transaction's payload, in block order. The offset points past
+// the 4-byte length prefix that precedes each transaction in the raw block.
+pub type TxIndex = Arc<Vec<(usize, usize)>>;
+
+pub struct BlockReader;
+
+impl BlockReader {
+    // Streams through `block` once, recording each transaction's (byte_offset, byte_len).
+    pub fn read(block: &[u8]) -> TxIndex {
+        let mut tx_index = Vec::new();
+        let mut offset = 0;
+        while offset < block.len() {
+            let len = read_len_prefix(&block[offset..offset + 4]);
+            let payload_offset = offset + 4;
+            tx_index.push((payload_offset, len));
+            offset = payload_offset + len;
+        }
+        Arc::new(tx_index)
+    }
+}
+
+fn read_len_prefix(bytes: &[u8]) -> usize {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+}
+
+// A raw block paired with the transaction index built from it. The index is built exactly
+// once, on construction, and then handed out by reference (or cloned as an `Arc`) to every
+// thread that needs to slice a transaction out of the block.
+pub struct IndexedBlock {
+    pub block: Arc<Vec<u8>>,
+    pub tx_index: TxIndex,
+}
+
+impl IndexedBlock {
+    pub fn new(block: Arc<Vec<u8>>) -> IndexedBlock {
+        let tx_index = BlockReader::read(&block);
+        IndexedBlock { block, tx_index }
+    }
+
+    pub fn num_txns(&self) -> usize {
+        self.tx_index.len()
+    }
+
+    pub fn block_len(&self) -> usize {
+        self.block.len()
+    }
+}
@@ -0,0 +1,35 @@
+// Pluggable hashing backend for the block-validation pipeline.
+//
+// Double-SHA256 over every transaction dominates the first stage of validation. Grouping
+// transactions into batches before handing them to a backend gives an implementation room to
+// amortize per-call overhead across many transactions at once, and is the seam where a future
+// SIMD or GPU-accelerated backend could be dropped in without touching the index builder or
+// the validator. `RingHashBackend` is the default: the same double-SHA256 this crate always
+// used, via `ring`; the backend is called once per batch and hashes that batch's transactions
+// one at a time.
+
+use ring::digest::{digest, SHA256};
+
+// Hashes a batch of transactions into TXIDs, in the same order they were given.
+// Implementations may parallelize, vectorize, or offload the batch however they like
+// internally; callers only see one TXID per input transaction, in order.
+pub trait HashBackend: Send + Sync {
+    fn hash_batch(&self, txns: &[&[u8]]) -> Vec<[u8; 32]>;
+}
+
+// Default CPU backend: double-SHA256 via `ring`, one transaction at a time.
+pub struct RingHashBackend;
+
+impl HashBackend for RingHashBackend {
+    fn hash_batch(&self, txns: &[&[u8]]) -> Vec<[u8; 32]> {
+        txns.iter().map(|data| hash_tx(data)).collect()
+    }
+}
+
+pub fn hash_tx(data: &[u8]) -> [u8; 32] {
+    let sha256 = digest(&SHA256, data);
+    let sha256d = digest(&SHA256, sha256.as_ref());
+    let mut hash256 = [0; 32];
+    hash256.clone_from_slice(sha256d.as_ref());
+    hash256
+}
@@ -0,0 +1,388 @@
+// This is a POC to show how TTOR can be validated in parallel without locks.
+//
+// At a high level, it works by splitting the block into bounds, where each bound is a
+// different part of the block. This happens in two stages. In the first stage, every bound
+// is hashed and the results are merged into one shared index of TXID -> Position. In stage
+// two, validation occurs in parallel. Each input needs one lookup in that index, and because
+// we only have to look backward, checking it is just a comparison of positions.
+//
+// Work is scheduled with Rayon's work-stealing thread pool instead of one OS thread per
+// bound. This lets us over-partition the block into more bounds than there are cores, so
+// idle threads can steal uneven work (some transactions cost more to hash or validate than
+// others) rather than sitting idle waiting on the slowest statically-assigned range.
+//
+// Transactions are variable-length, so `BlockReader` streams through the raw block once to
+// record each transaction's byte offset and length (see `block_reader`), and bounds are
+// contiguous byte ranges sized to split total bytes evenly rather than txn count.
+//
+// Hashing streams rather than blocks: each Rayon worker hashes its bound in fixed-size batches
+// (via a pluggable `HashBackend`, see `hash_backend`) and sends each completed batch of
+// `(txid, pos)` pairs to a collector over a bounded channel as soon as it's ready, rather than
+// hashing the whole bound before recording anything. There are several collectors, not one -
+// each bound is pinned to one collector shard (by index, so the same bound always lands on the
+// same shard), so the collectors themselves run concurrently with each other and with hashing
+// instead of every batch serializing onto a single thread. The shards are merged into one
+// `Index` only once every collector has drained its channel (see `build_index_with_backend`),
+// so that remains the barrier before validation starts.
+//
+// `validate_sequential` is the single-threaded baseline everything else is measured against
+// (see the `benches` crate), since naive multithreading can lose to it once per-thread
+// overhead outweighs the work being split.
+//
+// Terms:
+//
+//      Bound - [start_offset, end_offset) byte range defining which transactions a thread cares about
+//      Index - TXID -> absolute position, merged from every collector shard
+//
+// This implementation does not do real validation, nor build the merkle root. It only demonstrates
+// that the topological ordering is correct in parallel.
+
+extern crate rand;
+extern crate rayon;
+extern crate ring;
+
+pub mod block_reader;
+pub mod hash_backend;
+
+pub use block_reader::IndexedBlock;
+use block_reader::TxIndex;
+pub use hash_backend::{HashBackend, RingHashBackend};
+use hash_backend::hash_tx;
+use rand::{random, thread_rng, Rng};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Arc;
+use std::thread;
+
+pub type Bounds = Arc<Vec<(usize, usize)>>;
+pub type PTable = HashMap<[u8; 32], usize>;
+pub type Index = Arc<PTable>;
+
+// Each hashing batch covers this many transactions before its TXIDs are sent to a collector,
+// trading batch latency against per-send channel overhead.
+const HASH_BATCH_SIZE: usize = 1024;
+
+// Splits the block into `num_threads` contiguous byte ranges that each cover roughly
+// `block_len / num_threads` bytes, rather than an equal share of transactions. Bounds always
+// land on a transaction boundary (they're derived from the tx_index), so no thread ever needs
+// to see a transaction that straddles two bounds.
+pub fn setup_bounds(num_threads: usize, indexed_block: &IndexedBlock) -> Bounds {
+    let tx_index = &indexed_block.tx_index;
+    let block_len = indexed_block.block_len();
+    let mut bounds = Vec::<(usize, usize)>::new();
+    let mut start_offset = 0;
+    let mut tx_cursor = 0;
+    for thread in 0..num_threads {
+        let target = (thread + 1) * block_len / num_threads;
+        while tx_cursor < tx_index.len() && tx_index[tx_cursor].0 < target {
+            tx_cursor += 1;
+        }
+        let end_offset = if thread == num_threads - 1 || tx_cursor >= tx_index.len() {
+            block_len
+        } else {
+            tx_index[tx_cursor].0
+        };
+        bounds.push((start_offset, end_offset));
+        start_offset = end_offset;
+    }
+    Arc::new(bounds)
+}
+
+// Returns the range of txn indices whose payload offset falls inside `bound`. `tx_index` is
+// sorted by offset (it's built in a single forward pass), so this is a pair of binary searches.
+fn txns_in_bound(tx_index: &TxIndex, bound: &(usize, usize)) -> std::ops::Range<usize> {
+    let start = tx_index.partition_point(|&(offset, _)| offset < bound.0);
+    let end = tx_index.partition_point(|&(offset, _)| offset < bound.1);
+    start..end
+}
+
+// Hashes every bound with the default `RingHashBackend`. See `build_index_with_backend` for
+// the streaming pipeline; this is the entry point every caller that doesn't care about the
+// backend should use.
+pub fn build_index(indexed_block: &IndexedBlock, bounds: &Bounds) -> Index {
+    build_index_with_backend(indexed_block, bounds, &RingHashBackend)
+}
+
+// Hashes every bound in parallel over Rayon's work-stealing pool, streaming completed
+// `(txid, pos)` batches to one of several collector threads as soon as each batch of
+// `HASH_BATCH_SIZE` transactions is ready, rather than waiting for a bound's entire range.
+// `backend` does the actual hashing (see `hash_backend` for why that's pluggable). Spreading
+// collectors across `rayon::current_num_threads()` shards (rather than funneling every bound
+// through one) is what lets collection actually run concurrently with hashing instead of
+// becoming a single-thread bottleneck once enough hashing workers are feeding it. This is still
+// the barrier between hashing and validation: every collector must drain its channel (each
+// closes once every sender cloned from it is dropped, which happens when `par_iter` finishes)
+// before the shards are merged into the final `Index`.
+pub fn build_index_with_backend(
+    indexed_block: &IndexedBlock,
+    bounds: &Bounds,
+    backend: &dyn HashBackend,
+) -> Index {
+    let num_shards = rayon::current_num_threads().min(bounds.len().max(1));
+    let mut senders = Vec::with_capacity(num_shards);
+    let collectors: Vec<_> = (0..num_shards)
+        .map(|_| {
+            let (batch_sndr, batch_rcvr) = sync_channel::<Vec<([u8; 32], usize)>>(bounds.len());
+            senders.push(batch_sndr);
+            thread::spawn(move || {
+                let mut shard: PTable = HashMap::new();
+                for batch in batch_rcvr {
+                    shard.extend(batch);
+                }
+                shard
+            })
+        })
+        .collect();
+
+    bounds.par_iter().enumerate().for_each(|(i, bound)| {
+        let batch_sndr = senders[i % num_shards].clone();
+        hash_bound(bound, indexed_block, backend, &batch_sndr);
+    });
+    drop(senders);
+
+    let mut index = HashMap::new();
+    for collector in collectors {
+        index.extend(collector.join().unwrap());
+    }
+    Arc::new(index)
+}
+
+// Hashes one bound in fixed-size batches, sending each batch to its shard's collector as soon
+// as it's full rather than accumulating the whole bound in memory first. A partial final batch
+// is still sent, so the collector never halts on a bound whose txn count isn't a multiple of
+// `HASH_BATCH_SIZE`.
+fn hash_bound(
+    bound: &(usize, usize),
+    indexed_block: &IndexedBlock,
+    backend: &dyn HashBackend,
+    batch_sndr: &SyncSender<Vec<([u8; 32], usize)>>,
+) {
+    let tx_index = &indexed_block.tx_index;
+    let block = &indexed_block.block;
+    let mut txns = Vec::with_capacity(HASH_BATCH_SIZE);
+    let mut positions = Vec::with_capacity(HASH_BATCH_SIZE);
+    for n in txns_in_bound(tx_index, bound) {
+        let (offset, len) = tx_index[n];
+        txns.push(&block[offset..offset + len]);
+        positions.push(n);
+        if txns.len() == HASH_BATCH_SIZE {
+            send_batch(backend, &mut txns, &mut positions, batch_sndr);
+        }
+    }
+    if !txns.is_empty() {
+        send_batch(backend, &mut txns, &mut positions, batch_sndr);
+    }
+}
+
+fn send_batch(
+    backend: &dyn HashBackend,
+    txns: &mut Vec<&[u8]>,
+    positions: &mut Vec<usize>,
+    batch_sndr: &SyncSender<Vec<([u8; 32], usize)>>,
+) {
+    let ids = backend.hash_batch(txns);
+    batch_sndr.send(ids.into_iter().zip(positions.drain(..)).collect()).unwrap();
+    txns.clear();
+}
+
+// Validates every bound in parallel and returns `false` if any bound fails. Each bound stops at
+// its own first failure, but a failing bound does not stop the others from being checked.
+pub fn validate_all(indexed_block: &IndexedBlock, bounds: &Bounds, index: &Index) -> bool {
+    bounds
+        .par_iter()
+        .map(|bound| validate_range(bound, indexed_block, index))
+        .reduce(|| true, |a, b| a && b)
+}
+
+fn validate_range(bound: &(usize, usize), indexed_block: &IndexedBlock, index: &Index) -> bool {
+    let block = &indexed_block.block;
+    let tx_index = &indexed_block.tx_index;
+    for n in txns_in_bound(tx_index, bound) {
+        let (offset, _) = tx_index[n];
+        let tx_input = &block[offset..offset + 32];
+        if tx_input != [0; 32] {
+            // This txn has an input in the block; one lookup tells us its producer's absolute
+            // position, and the look-backward-only invariant just needs pos < n.
+            match index.get(tx_input) {
+                Some(pos) if *pos < n => {}
+                Some(pos) => {
+                    println!("Out of order: {} {}", n, *pos);
+                    return false;
+                }
+                None => {
+                    println!("Missing input: {}", n);
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+// Single-threaded baseline: one PTable, built and checked in a single forward pass over the
+// block. Every other path in this crate is measured against this to prove parallelism
+// actually pays for itself (see the `benches` crate group).
+pub fn validate_sequential(indexed_block: &IndexedBlock) -> bool {
+    let block = &indexed_block.block;
+    let tx_index = &indexed_block.tx_index;
+    let mut ptable = HashMap::new();
+    for n in 0..tx_index.len() {
+        let (offset, _) = tx_index[n];
+        let tx_input = &block[offset..offset + 32];
+        if tx_input != [0; 32] {
+            match ptable.get(tx_input) {
+                Some(pos) if *pos < n => {}
+                Some(pos) => {
+                    println!("Out of order: {} {}", n, *pos);
+                    return false;
+                }
+                None => {
+                    println!("Missing input: {}", n);
+                    return false;
+                }
+            }
+        }
+        ptable.insert(txid(n, indexed_block), n);
+    }
+    true
+}
+
+// In our model, each transaction is a 4-byte little-endian length prefix, followed by a
+// single input (or 32 zero bytes if it spends a UTXO from before this block), followed by
+// random filler out to that transaction's length.
+pub fn gen_block(num_txns: usize, tx_size: usize, num_reorder: usize, p_inside: f32) -> Vec<u8> {
+    let mut rng = thread_rng();
+    let mut block = Vec::new();
+    let mut offsets = Vec::<(usize, usize)>::with_capacity(num_txns);
+    for i in 0..num_txns {
+        let len = gen_tx_len(tx_size);
+        block.extend_from_slice(&(len as u32).to_le_bytes());
+        let payload_offset = block.len();
+        block.resize(payload_offset + len, 0);
+        offsets.push((payload_offset, len));
+
+        if i > 0 && random::<f32>() <= p_inside {
+            let prev_index = random::<usize>() % i;
+            let (prev_offset, prev_len) = offsets[prev_index];
+            let prev_hash = hash_tx(&block[prev_offset..prev_offset + prev_len]);
+            block[payload_offset..payload_offset + 32].clone_from_slice(&prev_hash);
+        }
+        rng.fill(&mut block[payload_offset + 32..payload_offset + len]);
+    }
+    reorder_block(num_reorder, &mut block, &offsets);
+    block
+}
+
+// Transaction lengths are randomized around `tx_size` (half to one-and-a-half of it) so the
+// block has genuinely uneven work for the hasher and validator to balance. Always at least one
+// byte longer than the 32-byte input every transaction carries, so two transactions that both
+// skip the input (all-zero, spending a pre-block UTXO) still get random filler of their own,
+// which makes it unlikely (though not impossible) that they hash to the same TXID.
+fn gen_tx_len(tx_size: usize) -> usize {
+    let min_len = (tx_size / 2).max(33);
+    let max_len = (tx_size * 3 / 2).max(min_len);
+    min_len + random::<usize>() % (max_len - min_len + 1)
+}
+
+fn reorder_block(n: usize, block: &mut [u8], offsets: &[(usize, usize)]) {
+    let num_txns = offsets.len();
+    for _ in 0..n {
+        let (a, _) = offsets[random::<usize>() % num_txns];
+        let (b, _) = offsets[random::<usize>() % num_txns];
+        let a_data = block[a..a + 32].to_vec();
+        let b_data = block[b..b + 32].to_vec();
+        block[a..a + 32].clone_from_slice(&b_data);
+        block[b..b + 32].clone_from_slice(&a_data);
+    }
+}
+
+fn txid(n: usize, indexed_block: &IndexedBlock) -> [u8; 32] {
+    let (offset, len) = indexed_block.tx_index[n];
+    hash_tx(&indexed_block.block[offset..offset + len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_block(txn_payloads: &[Vec<u8>]) -> Vec<u8> {
+        let mut block = Vec::new();
+        for payload in txn_payloads {
+            block.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            block.extend_from_slice(payload);
+        }
+        block
+    }
+
+    // `marker` only needs to make otherwise-identical payloads hash differently.
+    fn payload(input: [u8; 32], marker: u8) -> Vec<u8> {
+        let mut payload = input.to_vec();
+        payload.extend_from_slice(&[marker; 8]);
+        payload
+    }
+
+    fn indexed(block: Vec<u8>) -> IndexedBlock {
+        IndexedBlock::new(Arc::new(block))
+    }
+
+    fn validate_parallel(indexed_block: &IndexedBlock, num_threads: usize) -> bool {
+        let bounds = setup_bounds(num_threads, indexed_block);
+        let index = build_index(indexed_block, &bounds);
+        validate_all(indexed_block, &bounds, &index)
+    }
+
+    // The whole point of build_index's sharded-collector streaming pipeline is that it produces
+    // the same answer as the single-threaded, single-PTable baseline no matter how the block is
+    // split. Sweep bound counts, txn/tx sizes, and reorder rates to make sure that holds.
+    #[test]
+    fn parallel_matches_sequential_across_workloads() {
+        let workloads: &[(usize, usize, usize, f32)] = &[
+            (200, 64, 0, 0.0),
+            (200, 64, 10, 0.3),
+            (500, 128, 0, 0.8),
+            (500, 32, 25, 0.5),
+        ];
+        for &(num_txns, tx_size, num_reorder, p_inside) in workloads {
+            let block = gen_block(num_txns, tx_size, num_reorder, p_inside);
+            let indexed_block = indexed(block);
+            let expected = validate_sequential(&indexed_block);
+            for &num_threads in &[1, 2, 3, 7] {
+                assert_eq!(
+                    validate_parallel(&indexed_block, num_threads),
+                    expected,
+                    "num_threads={} num_txns={} tx_size={} num_reorder={} p_inside={}",
+                    num_threads,
+                    num_txns,
+                    tx_size,
+                    num_reorder,
+                    p_inside
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn detects_forward_reference() {
+        // C has no input; B references C's TXID even though C comes after it in the block.
+        let payload_c = payload([0; 32], 2);
+        let txid_c = hash_tx(&payload_c);
+        let block = raw_block(&[payload([0; 32], 1), payload(txid_c, 3), payload_c]);
+        let indexed_block = indexed(block);
+
+        assert!(!validate_sequential(&indexed_block));
+        assert!(!validate_parallel(&indexed_block, 3));
+    }
+
+    #[test]
+    fn detects_missing_input() {
+        // This input doesn't match any TXID produced in the block.
+        let mut missing_input = [0; 32];
+        missing_input[0] = 1;
+        let block = raw_block(&[payload(missing_input, 1)]);
+        let indexed_block = indexed(block);
+
+        assert!(!validate_sequential(&indexed_block));
+        assert!(!validate_parallel(&indexed_block, 2));
+    }
+}